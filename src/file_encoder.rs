@@ -4,17 +4,323 @@
 
 use crate::ui::EguiExt;
 use anyhow::Result;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::codecs::gif::{GifEncoder, Repeat};
 use image::codecs::jpeg::JpegEncoder;
 use image::codecs::png::{CompressionType, PngEncoder};
-use image::{DynamicImage, ImageEncoder};
-use notan::egui::Ui;
+use image::{Delay, DynamicImage, Frame, ImageEncoder};
+use notan::egui::{ComboBox, Ui};
+use png::Encoder as ApngEncoder;
 use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::path::Path;
 use std::io::{BufWriter, Write};
-use strum::{Display, EnumIter};
+use std::time::Duration;
+use strum::{Display, EnumIter, IntoEnumIterator};
 use tempfile::Builder;
 use anyhow::Context;
+use webp::{Encoder, PixelLayout};
+
+/// Embedded color and orientation metadata carried over from the source
+/// file so it isn't silently dropped on save.
+#[derive(Default, Debug, Clone)]
+pub struct ImageMetadata {
+    pub icc_profile: Option<Vec<u8>>,
+    pub exif: Option<Vec<u8>>,
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    const POLY: u32 = 0xEDB88320;
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = 0u32.wrapping_sub(crc & 1);
+            crc = (crc >> 1) ^ (POLY & mask);
+        }
+    }
+    !crc
+}
+
+fn png_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut chunk = Vec::with_capacity(8 + data.len() + 4);
+    chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    chunk.extend_from_slice(chunk_type);
+    chunk.extend_from_slice(data);
+    chunk.extend_from_slice(&crc32(&chunk[4..]).to_be_bytes());
+    chunk
+}
+
+/// Splices an `iCCP` and/or `eXIf` chunk right after `IHDR`, which is
+/// always the first chunk in a PNG produced by `PngEncoder`.
+fn insert_png_metadata(png_bytes: &[u8], metadata: &ImageMetadata) -> Result<Vec<u8>> {
+    const IHDR_END: usize = 8 /* signature */ + 4 + 4 + 13 + 4 /* length+type+data+crc */;
+
+    let mut out = Vec::with_capacity(png_bytes.len() + 4096);
+    out.extend_from_slice(&png_bytes[..IHDR_END]);
+
+    if let Some(icc) = &metadata.icc_profile {
+        let mut data = b"icc\0".to_vec(); // profile name + null terminator
+        data.push(0); // compression method: zlib
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(icc)?;
+        data.extend_from_slice(&encoder.finish()?);
+        out.extend_from_slice(&png_chunk(b"iCCP", &data));
+    }
+
+    if let Some(exif) = &metadata.exif {
+        out.extend_from_slice(&png_chunk(b"eXIf", exif));
+    }
+
+    out.extend_from_slice(&png_bytes[IHDR_END..]);
+    Ok(out)
+}
+
+fn jpeg_app_segment(marker: u8, payload: &[u8]) -> Result<Vec<u8>> {
+    let len = payload.len() + 2;
+    if len > u16::MAX as usize {
+        anyhow::bail!(
+            "JPEG APP segment payload of {} bytes exceeds the 64KB marker limit",
+            payload.len()
+        );
+    }
+    let mut segment = Vec::with_capacity(4 + payload.len());
+    segment.push(0xFF);
+    segment.push(marker);
+    segment.extend_from_slice(&(len as u16).to_be_bytes());
+    segment.extend_from_slice(payload);
+    Ok(segment)
+}
+
+/// Inserts an `APP1`/Exif segment and, split across `APP2`/`ICC_PROFILE`
+/// segments as the marker's 64KB limit requires, right after the `SOI`
+/// marker.
+///
+/// Unlike `ICC_PROFILE`, Exif has no standard convention for spanning
+/// multiple `APP1` segments, so an oversized Exif blob is rejected rather
+/// than silently split or truncated into something compliant readers
+/// wouldn't understand anyway.
+fn insert_jpeg_metadata(jpeg_bytes: &[u8], metadata: &ImageMetadata) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(jpeg_bytes.len() + 4096);
+    out.extend_from_slice(&jpeg_bytes[..2]); // SOI
+
+    if let Some(exif) = &metadata.exif {
+        let mut payload = b"Exif\0\0".to_vec();
+        payload.extend_from_slice(exif);
+        out.extend_from_slice(
+            &jpeg_app_segment(0xE1, &payload)
+                .context("Exif metadata is too large to fit in a single JPEG APP1 segment")?,
+        );
+    }
+
+    if let Some(icc) = &metadata.icc_profile {
+        const MAX_CHUNK: usize = 65533 - 14; // "ICC_PROFILE\0" + seq + count
+        let chunks: Vec<&[u8]> = icc.chunks(MAX_CHUNK).collect();
+        let total = chunks.len() as u8;
+        for (i, chunk) in chunks.iter().enumerate() {
+            let mut payload = b"ICC_PROFILE\0".to_vec();
+            payload.push((i + 1) as u8);
+            payload.push(total);
+            payload.extend_from_slice(chunk);
+            out.extend_from_slice(&jpeg_app_segment(0xE2, &payload)?);
+        }
+    }
+
+    out.extend_from_slice(&jpeg_bytes[2..]);
+    Ok(out)
+}
+
+fn riff_chunk(fourcc: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut chunk = Vec::with_capacity(8 + data.len() + 1);
+    chunk.extend_from_slice(fourcc);
+    chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        chunk.push(0); // RIFF chunks are padded to an even size
+    }
+    chunk
+}
+
+fn webp_vp8x_chunk(
+    width: u32,
+    height: u32,
+    has_alpha: bool,
+    has_icc: bool,
+    has_exif: bool,
+    has_anim: bool,
+) -> Vec<u8> {
+    const ICC_FLAG: u8 = 0x20;
+    const ALPHA_FLAG: u8 = 0x10;
+    const EXIF_FLAG: u8 = 0x08;
+    const ANIM_FLAG: u8 = 0x02;
+
+    let mut flags = 0u8;
+    if has_icc {
+        flags |= ICC_FLAG;
+    }
+    if has_alpha {
+        flags |= ALPHA_FLAG;
+    }
+    if has_exif {
+        flags |= EXIF_FLAG;
+    }
+    if has_anim {
+        flags |= ANIM_FLAG;
+    }
+
+    let mut data = vec![flags, 0, 0, 0];
+    data.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+    data.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+    riff_chunk(b"VP8X", &data)
+}
+
+fn webp_anim_chunk(loop_count: u16) -> Vec<u8> {
+    let mut data = vec![0u8; 4]; // background color: unspecified
+    data.extend_from_slice(&loop_count.to_le_bytes());
+    riff_chunk(b"ANIM", &data)
+}
+
+/// Wraps one frame's already-encoded image chunks (`VP8L`, or `VP8 ` with an
+/// optional leading `ALPH`) in an `ANMF` subchunk, full-canvas and blended,
+/// per the WebP animation spec.
+fn webp_anmf_chunk(width: u32, height: u32, duration_ms: u32, image_chunk: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(16 + image_chunk.len());
+    data.extend_from_slice(&0u32.to_le_bytes()[..3]); // frame X
+    data.extend_from_slice(&0u32.to_le_bytes()[..3]); // frame Y
+    data.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+    data.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+    data.extend_from_slice(&duration_ms.to_le_bytes()[..3]);
+    data.push(0); // reserved + blend + dispose: default (blend, no dispose)
+    data.extend_from_slice(image_chunk);
+    riff_chunk(b"ANMF", &data)
+}
+
+/// Returns the bitstream chunk(s) of a WebP encoded by the `webp` crate's
+/// simple encoder, stripped of the `RIFF`/size/`WEBP` header.
+///
+/// Usually that's a bare `VP8 `/`VP8L` chunk, but libwebp's lossy path
+/// can't carry alpha in-band, so for a lossy image with an alpha channel
+/// the simple encoder already emits its own extended `VP8X`/`ALPH`/`VP8 `
+/// container. Unwrap that container too instead of passing it through
+/// verbatim, so callers never nest a second `VP8X` around an already
+/// extended WebP.
+fn webp_image_chunks(webp_bytes: &[u8]) -> &[u8] {
+    const HEADER_LEN: usize = 12; // "RIFF" + size(4) + "WEBP"
+    if &webp_bytes[HEADER_LEN..HEADER_LEN + 4] == b"VP8X" {
+        let vp8x_size =
+            u32::from_le_bytes(webp_bytes[HEADER_LEN + 4..HEADER_LEN + 8].try_into().unwrap())
+                as usize;
+        let vp8x_len = 8 + vp8x_size + (vp8x_size % 2); // chunk header + payload + padding
+        &webp_bytes[HEADER_LEN + vp8x_len..]
+    } else {
+        &webp_bytes[HEADER_LEN..]
+    }
+}
+
+/// Upgrades a "simple" WebP to the extended format by adding a `VP8X`
+/// header plus `ICCP`/`EXIF` RIFF chunks around the image data.
+fn insert_webp_metadata(
+    webp_bytes: &[u8],
+    width: u32,
+    height: u32,
+    has_alpha: bool,
+    metadata: &ImageMetadata,
+) -> Vec<u8> {
+    let image_chunks = webp_image_chunks(webp_bytes);
+
+    let mut payload = b"WEBP".to_vec();
+    payload.extend_from_slice(&webp_vp8x_chunk(
+        width,
+        height,
+        has_alpha,
+        metadata.icc_profile.is_some(),
+        metadata.exif.is_some(),
+        false,
+    ));
+    if let Some(icc) = &metadata.icc_profile {
+        payload.extend_from_slice(&riff_chunk(b"ICCP", icc));
+    }
+    payload.extend_from_slice(image_chunks);
+    if let Some(exif) = &metadata.exif {
+        payload.extend_from_slice(&riff_chunk(b"EXIF", exif));
+    }
+
+    let mut out = Vec::with_capacity(payload.len() + 8);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(&payload);
+    out
+}
+
+/// Encodes a single image with the `webp` crate's simple encoder, returning
+/// its dimensions and alpha-channel presence alongside the encoded bytes so
+/// callers can mux in metadata.
+fn encode_webp(image: &DynamicImage, quality: &WebpQuality) -> (u32, u32, bool, Vec<u8>) {
+    let has_alpha = image.color().has_alpha();
+    let (layout, width, height, raw): (PixelLayout, u32, u32, Vec<u8>) = if has_alpha {
+        let rgba_image = image.to_rgba8();
+        (
+            PixelLayout::Rgba,
+            rgba_image.width(),
+            rgba_image.height(),
+            rgba_image.into_raw(),
+        )
+    } else {
+        let rgb_image = image.to_rgb8();
+        (
+            PixelLayout::Rgb,
+            rgb_image.width(),
+            rgb_image.height(),
+            rgb_image.into_raw(),
+        )
+    };
+
+    let encoder = Encoder::new(&raw, layout, width, height);
+    let encoded = match quality {
+        WebpQuality::Lossless => encoder.encode_lossless(),
+        WebpQuality::Lossy(q) => encoder.encode(*q),
+    };
+    (width, height, has_alpha, encoded.to_vec())
+}
+
+/// Encodes each frame with the `webp` crate's simple encoder and muxes the
+/// results into an extended (`VP8X`/`ANIM`/`ANMF`) animated WebP container.
+fn save_animated_webp(
+    frames: &[(DynamicImage, Duration)],
+    quality: &WebpQuality,
+    loop_count: &LoopCount,
+) -> Vec<u8> {
+    let (width, height) = frames
+        .first()
+        .map(|(image, _)| (image.width(), image.height()))
+        .unwrap_or_default();
+    let has_alpha = frames.iter().any(|(image, _)| image.color().has_alpha());
+
+    let mut payload = b"WEBP".to_vec();
+    payload.extend_from_slice(&webp_vp8x_chunk(width, height, has_alpha, false, false, true));
+    payload.extend_from_slice(&webp_anim_chunk(loop_count.num_plays()));
+
+    for (image, duration) in frames {
+        let (_, _, _, encoded) = encode_webp(image, quality);
+        // A lossy frame with alpha is already its own extended container
+        // (VP8X/ALPH/VP8); unwrap it to its ALPH/VP8 chunks so no VP8X is
+        // nested inside ANMF, whose frame data holds only image chunks.
+        let image_chunk = webp_image_chunks(&encoded);
+        payload.extend_from_slice(&webp_anmf_chunk(
+            width,
+            height,
+            duration.as_millis() as u32,
+            image_chunk,
+        ));
+    }
+
+    let mut out = Vec::with_capacity(payload.len() + 8);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(&payload);
+    out
+}
 
 #[derive(Default, Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Display, EnumIter)]
 
@@ -25,47 +331,178 @@ pub enum CompressionLevel {
     Fast,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Display, EnumIter)]
+#[derive(Default, Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Display, EnumIter)]
+pub enum PngFilter {
+    NoFilter,
+    Sub,
+    Up,
+    #[default]
+    Average,
+    Paeth,
+    /// Picks whichever filter minimizes the sum of absolute differences on
+    /// each scanline, independently. Encoded via the `png` crate directly
+    /// (see [`FileEncoder::save`]'s `Png` arm), since `image`'s `PngEncoder`
+    /// only supports a single fixed filter for the whole image.
+    Adaptive,
+}
+
+impl From<&PngFilter> for image::codecs::png::FilterType {
+    fn from(filter: &PngFilter) -> Self {
+        match filter {
+            PngFilter::NoFilter => image::codecs::png::FilterType::NoFilter,
+            PngFilter::Sub => image::codecs::png::FilterType::Sub,
+            PngFilter::Up => image::codecs::png::FilterType::Up,
+            PngFilter::Average => image::codecs::png::FilterType::Avg,
+            PngFilter::Paeth | PngFilter::Adaptive => image::codecs::png::FilterType::Paeth,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum WebpQuality {
+    Lossless,
+    Lossy(f32),
+}
+
+impl Default for WebpQuality {
+    fn default() -> Self {
+        Self::Lossy(80.)
+    }
+}
+
+impl WebpQuality {
+    fn clamped(quality: f32) -> Self {
+        Self::Lossy(quality.clamp(0., 100.))
+    }
+}
+
+fn webp_quality_ui(ui: &mut Ui, quality: &mut WebpQuality) {
+    let mut lossless = matches!(quality, WebpQuality::Lossless);
+    ui.checkbox(&mut lossless, "Lossless");
+
+    if lossless {
+        *quality = WebpQuality::Lossless;
+    } else {
+        let mut q = match quality {
+            WebpQuality::Lossy(q) => *q,
+            WebpQuality::Lossless => 80.,
+        };
+        ui.label("Quality");
+        ui.styled_slider(&mut q, 0.0..=100.0);
+        *quality = WebpQuality::clamped(q);
+    }
+}
+
+/// How many times an animated image should play before stopping.
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum LoopCount {
+    #[default]
+    Infinite,
+    Finite(u16),
+}
+
+impl LoopCount {
+    /// `0` means "loop forever" in both the GIF and WebP animation formats.
+    fn num_plays(&self) -> u16 {
+        match self {
+            LoopCount::Infinite => 0,
+            LoopCount::Finite(n) => *n,
+        }
+    }
+}
+
+fn loop_count_ui(ui: &mut Ui, loop_count: &mut LoopCount) {
+    let mut infinite = matches!(loop_count, LoopCount::Infinite);
+    ui.checkbox(&mut infinite, "Loop forever");
+
+    if infinite {
+        *loop_count = LoopCount::Infinite;
+    } else {
+        let mut count = match loop_count {
+            LoopCount::Finite(n) => *n,
+            LoopCount::Infinite => 1,
+        };
+        ui.label("Loop count");
+        ui.styled_slider(&mut count, 1..=u16::MAX);
+        *loop_count = LoopCount::Finite(count);
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Display, EnumIter)]
 pub enum FileEncoder {
     Jpg { quality: u32 },
-    Png { compressionlevel: CompressionLevel },
+    Png {
+        compressionlevel: CompressionLevel,
+        filter: PngFilter,
+    },
     Bmp,
-    WebP,
+    WebP { quality: WebpQuality },
+    Qoi,
+    Gif { loop_count: LoopCount },
+    #[strum(to_string = "webp")]
+    AnimatedWebP {
+        quality: WebpQuality,
+        loop_count: LoopCount,
+    },
+    Apng { loop_count: LoopCount },
 }
 
 impl Default for FileEncoder {
     fn default() -> Self {
         Self::Png {
             compressionlevel: CompressionLevel::Default,
+            filter: PngFilter::default(),
         }
     }
 }
 
 impl FileEncoder {
-    pub fn matching_variant(path: &Path, variants: &Vec<Self>) -> Self {
+    /// Picks the variant in `variants` matching `path`'s extension.
+    ///
+    /// `frame_count` disambiguates extensions shared by both an animated
+    /// and a non-animated variant (currently only `webp`, between
+    /// [`FileEncoder::WebP`] and [`FileEncoder::AnimatedWebP`]): a variant
+    /// whose [`FileEncoder::is_animated`] matches `frame_count > 1` is
+    /// preferred, falling back to any extension match.
+    pub fn matching_variant(path: &Path, variants: &Vec<Self>, frame_count: usize) -> Self {
         let ext = path
             .extension()
             .map(|e| e.to_string_lossy().to_string())
             .unwrap_or_default()
             .to_lowercase()
             .replace("jpeg", "jpg");
+        let is_animated = frame_count > 1;
 
-        for v in variants {
-            if v.ext() == ext {
-                return v.clone();
-            }
-        }
-
-        Self::Png {
-            compressionlevel: CompressionLevel::Default,
-        }
+        variants
+            .iter()
+            .find(|v| v.ext() == ext && v.is_animated() == is_animated)
+            .or_else(|| variants.iter().find(|v| v.ext() == ext))
+            .cloned()
+            .unwrap_or_else(|| Self::Png {
+                compressionlevel: CompressionLevel::Default,
+                filter: PngFilter::default(),
+            })
     }
 
     pub fn ext(&self) -> String {
         self.to_string().to_lowercase()
     }
 
-    pub fn save(&self, image: &DynamicImage, path: &Path) -> Result<()> {
+    /// Whether this variant writes multi-frame output via
+    /// [`FileEncoder::save_animation`] rather than flattening to one frame.
+    pub fn is_animated(&self) -> bool {
+        matches!(
+            self,
+            FileEncoder::Gif { .. } | FileEncoder::AnimatedWebP { .. } | FileEncoder::Apng { .. }
+        )
+    }
+
+    pub fn save(
+        &self,
+        image: &DynamicImage,
+        path: &Path,
+        metadata: Option<&ImageMetadata>,
+    ) -> Result<()> {
         let parent_dir = path.parent().unwrap_or_else(|| Path::new("."));
         let mut tmp_file = Builder::new()
             .suffix(".tmp")
@@ -79,24 +516,103 @@ impl FileEncoder {
             match self {
                 FileEncoder::Jpg { quality } => {
                     let rgb_image = image.to_rgb8();
-                    JpegEncoder::new_with_quality(&mut writer, *quality as u8)
-                        .write_image(
-                            rgb_image.as_raw(),
-                            rgb_image.width(),
-                            rgb_image.height(),
-                            image::ExtendedColorType::Rgb8,
-                        )?;
+                    let mut encoded = Vec::new();
+                    JpegEncoder::new_with_quality(&mut encoded, *quality as u8).write_image(
+                        rgb_image.as_raw(),
+                        rgb_image.width(),
+                        rgb_image.height(),
+                        image::ExtendedColorType::Rgb8,
+                    )?;
+
+                    if let Some(metadata) = metadata {
+                        encoded = insert_jpeg_metadata(&encoded, metadata)?;
+                    }
+                    writer.write_all(&encoded)?;
                 }
-                FileEncoder::Png { compressionlevel } => {
-                    let compression = match compressionlevel {
-                        CompressionLevel::Best => CompressionType::Best,
-                        CompressionLevel::Default => CompressionType::Default,
-                        CompressionLevel::Fast => CompressionType::Fast,
-                    };
+                FileEncoder::Png {
+                    compressionlevel,
+                    filter,
+                } => {
+                    let mut encoded = Vec::new();
+
+                    if matches!(filter, PngFilter::Adaptive) {
+                        // `image`'s PngEncoder only supports one fixed filter
+                        // for the whole image; go through the `png` crate
+                        // directly (as the Apng arm below already does) to
+                        // get its real per-scanline adaptive filtering.
+                        let has_alpha = image.color().has_alpha();
+                        let (color_type, raw): (png::ColorType, Vec<u8>) = if has_alpha {
+                            (png::ColorType::Rgba, image.to_rgba8().into_raw())
+                        } else {
+                            (png::ColorType::Rgb, image.to_rgb8().into_raw())
+                        };
 
+                        let mut png_encoder =
+                            png::Encoder::new(&mut encoded, image.width(), image.height());
+                        png_encoder.set_color(color_type);
+                        png_encoder.set_depth(png::BitDepth::Eight);
+                        png_encoder.set_compression(match compressionlevel {
+                            CompressionLevel::Best => png::Compression::Best,
+                            CompressionLevel::Default => png::Compression::Default,
+                            CompressionLevel::Fast => png::Compression::Fast,
+                        });
+                        png_encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive);
+                        png_encoder.write_header()?.write_image_data(&raw)?;
+                    } else {
+                        let compression = match compressionlevel {
+                            CompressionLevel::Best => CompressionType::Best,
+                            CompressionLevel::Default => CompressionType::Default,
+                            CompressionLevel::Fast => CompressionType::Fast,
+                        };
+
+                        PngEncoder::new_with_quality(&mut encoded, compression, filter.into())
+                            .write_image(
+                                image.as_bytes(),
+                                image.width(),
+                                image.height(),
+                                image.color().into(),
+                            )?;
+                    }
+
+                    if let Some(metadata) = metadata {
+                        encoded = insert_png_metadata(&encoded, metadata)?;
+                    }
+                    writer.write_all(&encoded)?;
+                }
+                FileEncoder::Bmp => {
+                    // BMP has no standard container for ICC/EXIF; skip silently.
+                    image.write_to(&mut writer, image::ImageFormat::Bmp)?;
+                }
+                FileEncoder::WebP { quality } | FileEncoder::AnimatedWebP { quality, .. } => {
+                    let (width, height, has_alpha, encoded) = encode_webp(image, quality);
+                    let encoded = match metadata {
+                        Some(metadata) => {
+                            insert_webp_metadata(&encoded, width, height, has_alpha, metadata)
+                        }
+                        None => encoded,
+                    };
+                    writer.write_all(&encoded)?;
+                }
+                FileEncoder::Qoi => {
+                    // QOI has no standard container for ICC/EXIF; skip silently.
+                    if image.color().has_alpha() {
+                        DynamicImage::ImageRgba8(image.to_rgba8())
+                            .write_to(&mut writer, image::ImageFormat::Qoi)?;
+                    } else {
+                        DynamicImage::ImageRgb8(image.to_rgb8())
+                            .write_to(&mut writer, image::ImageFormat::Qoi)?;
+                    }
+                }
+                FileEncoder::Gif { .. } => {
+                    // A single frame has no loop count to honor; write one GIF frame.
+                    image.write_to(&mut writer, image::ImageFormat::Gif)?;
+                }
+                FileEncoder::Apng { .. } => {
+                    // A single frame is just a plain PNG; fall back to default settings.
+                    let mut encoded = Vec::new();
                     PngEncoder::new_with_quality(
-                        &mut writer,
-                        compression,
+                        &mut encoded,
+                        CompressionType::Default,
                         image::codecs::png::FilterType::default(),
                     )
                     .write_image(
@@ -105,12 +621,11 @@ impl FileEncoder {
                         image.height(),
                         image.color().into(),
                     )?;
-                }
-                FileEncoder::Bmp => {
-                    image.write_to(&mut writer, image::ImageFormat::Bmp)?;
-                }
-                FileEncoder::WebP => {
-                    image.write_to(&mut writer, image::ImageFormat::WebP)?;
+
+                    if let Some(metadata) = metadata {
+                        encoded = insert_png_metadata(&encoded, metadata)?;
+                    }
+                    writer.write_all(&encoded)?;
                 }
             }
         } // Buffer flushes here
@@ -131,10 +646,315 @@ impl FileEncoder {
                 ui.styled_slider(quality, 0..=100);
             }
             FileEncoder::Png {
-                compressionlevel: _,
-            } => {}
+                compressionlevel,
+                filter,
+            } => {
+                ui.label("Compression");
+                ComboBox::from_id_source("png_compressionlevel")
+                    .selected_text(compressionlevel.to_string())
+                    .show_ui(ui, |ui| {
+                        for level in CompressionLevel::iter() {
+                            let text = level.to_string();
+                            ui.selectable_value(compressionlevel, level, text);
+                        }
+                    });
+
+                ui.label("Filter");
+                ComboBox::from_id_source("png_filter")
+                    .selected_text(filter.to_string())
+                    .show_ui(ui, |ui| {
+                        for f in PngFilter::iter() {
+                            let text = f.to_string();
+                            ui.selectable_value(filter, f, text);
+                        }
+                    });
+            }
             FileEncoder::Bmp => {}
-            FileEncoder::WebP => {}
+            FileEncoder::WebP { quality } => webp_quality_ui(ui, quality),
+            FileEncoder::Qoi => {}
+            FileEncoder::Gif { loop_count } => loop_count_ui(ui, loop_count),
+            FileEncoder::AnimatedWebP {
+                quality,
+                loop_count,
+            } => {
+                webp_quality_ui(ui, quality);
+                loop_count_ui(ui, loop_count);
+            }
+            FileEncoder::Apng { loop_count } => loop_count_ui(ui, loop_count),
         }
     }
+
+    /// Writes a multi-frame source (animated GIF/WebP/APNG) back out as an
+    /// animation rather than flattening it to a single frame, using the same
+    /// atomic temp-file-then-rename write strategy as [`FileEncoder::save`].
+    /// A variant that is not [`FileEncoder::is_animated`] saves only the first
+    /// frame, through [`FileEncoder::save`] without metadata.
+    ///
+    /// Only formats `image`/`webp`/`png` can mux directly are supported.
+    /// Shelling out to `ffmpeg` for formats they can't (e.g. animated
+    /// AVIF/MP4) is deferred to a future change, not implemented here.
+    pub fn save_animation(&self, frames: &[(DynamicImage, Duration)], path: &Path) -> Result<()> {
+        if !matches!(
+            self,
+            FileEncoder::Gif { .. } | FileEncoder::AnimatedWebP { .. } | FileEncoder::Apng { .. }
+        ) {
+            // This encoder has no animation support; fall back to the first frame.
+            return match frames.first() {
+                Some((image, _)) => self.save(image, path, None),
+                None => Ok(()),
+            };
+        }
+
+        if frames.is_empty() {
+            return Ok(());
+        }
+
+        let parent_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let mut tmp_file = Builder::new()
+            .suffix(".tmp")
+            .tempfile_in(parent_dir)
+            .context("Failed to create temporary file")?;
+
+        {
+            let mut writer = BufWriter::with_capacity(64 * 1024, &mut tmp_file);
+
+            match self {
+                FileEncoder::Gif { loop_count } => {
+                    let mut encoder = GifEncoder::new(&mut writer);
+                    encoder.set_repeat(match loop_count {
+                        LoopCount::Infinite => Repeat::Infinite,
+                        LoopCount::Finite(n) => Repeat::Finite(*n),
+                    })?;
+                    for (image, duration) in frames {
+                        let frame = Frame::from_parts(
+                            image.to_rgba8(),
+                            0,
+                            0,
+                            Delay::from_saturating_duration(*duration),
+                        );
+                        encoder.encode_frame(frame)?;
+                    }
+                }
+                FileEncoder::AnimatedWebP {
+                    quality,
+                    loop_count,
+                } => {
+                    writer.write_all(&save_animated_webp(frames, quality, loop_count))?;
+                }
+                FileEncoder::Apng { loop_count } => {
+                    let (width, height) = frames
+                        .first()
+                        .map(|(image, _)| (image.width(), image.height()))
+                        .unwrap_or_default();
+
+                    let mut encoder = ApngEncoder::new(&mut writer, width, height);
+                    encoder.set_color(png::ColorType::Rgba);
+                    encoder.set_depth(png::BitDepth::Eight);
+                    encoder.set_animated(frames.len() as u32, loop_count.num_plays() as u32)?;
+                    let mut apng_writer = encoder.write_header()?;
+                    for (image, duration) in frames {
+                        let millis = duration.as_millis().min(u16::MAX as u128) as u16;
+                        apng_writer.set_frame_delay(millis, 1000)?;
+                        apng_writer.write_image_data(image.to_rgba8().as_raw())?;
+                    }
+                    apng_writer.finish()?;
+                }
+                _ => unreachable!("checked for an animated variant above"),
+            }
+        } // Buffer flushes here
+
+        tmp_file.as_file().sync_all().context("Failed to sync to disk")?;
+        tmp_file.persist(path).context("Failed to persist file")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+    use image::RgbaImage;
+
+    fn solid_rgba(width: u32, height: u32, pixel: Rgba<u8>) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, pixel))
+    }
+
+    #[test]
+    fn png_metadata_round_trips_icc_and_exif() {
+        let image = solid_rgba(4, 4, Rgba([10, 20, 30, 255]));
+        let mut encoded = Vec::new();
+        PngEncoder::new_with_quality(
+            &mut encoded,
+            CompressionType::Default,
+            image::codecs::png::FilterType::Paeth,
+        )
+        .write_image(
+            image.as_bytes(),
+            image.width(),
+            image.height(),
+            image.color().into(),
+        )
+        .unwrap();
+
+        let metadata = ImageMetadata {
+            icc_profile: Some(b"fake icc profile".to_vec()),
+            exif: Some(b"fake exif blob".to_vec()),
+        };
+        let with_metadata = insert_png_metadata(&encoded, &metadata).unwrap();
+
+        let decoder = png::Decoder::new(with_metadata.as_slice());
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!(
+            info.exif_metadata.as_deref(),
+            Some(b"fake exif blob".as_slice())
+        );
+        assert!(info.icc_profile.is_some());
+    }
+
+    #[test]
+    fn png_adaptive_filter_round_trips_pixels() {
+        let mut image = RgbaImage::from_pixel(5, 5, Rgba([0, 0, 0, 255]));
+        // Vary pixels so scanlines actually differ, giving the adaptive
+        // filter something real to choose between.
+        for (i, pixel) in image.pixels_mut().enumerate() {
+            pixel.0[0] = (i * 37) as u8;
+        }
+        let image = DynamicImage::ImageRgba8(image);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.png");
+        let encoder = FileEncoder::Png {
+            compressionlevel: CompressionLevel::Default,
+            filter: PngFilter::Adaptive,
+        };
+        encoder.save(&image, &path, None).unwrap();
+
+        let decoded = image::open(&path).unwrap();
+        assert_eq!(decoded.to_rgba8(), image.to_rgba8());
+    }
+
+    #[test]
+    fn jpeg_metadata_round_trips_exif_and_chunks_large_icc() {
+        let image = solid_rgba(4, 4, Rgba([10, 20, 30, 255])).to_rgb8();
+        let mut encoded = Vec::new();
+        JpegEncoder::new_with_quality(&mut encoded, 90)
+            .write_image(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                image::ExtendedColorType::Rgb8,
+            )
+            .unwrap();
+
+        // Larger than a single APP2 segment can hold, so it must be split.
+        let icc = vec![7u8; 130_000];
+        let metadata = ImageMetadata {
+            icc_profile: Some(icc),
+            exif: Some(b"exif payload".to_vec()),
+        };
+        let with_metadata = insert_jpeg_metadata(&encoded, &metadata).unwrap();
+
+        // Still a valid, decodable JPEG.
+        image::load_from_memory_with_format(&with_metadata, image::ImageFormat::Jpeg).unwrap();
+
+        let mut exif_segment = b"Exif\0\0".to_vec();
+        exif_segment.extend_from_slice(b"exif payload");
+        assert!(with_metadata
+            .windows(exif_segment.len())
+            .any(|w| w == exif_segment.as_slice()));
+
+        let app2_segments = with_metadata
+            .windows(2)
+            .filter(|w| w[0] == 0xFF && w[1] == 0xE2)
+            .count();
+        assert!(
+            app2_segments > 1,
+            "oversized ICC profile should span more than one APP2 segment"
+        );
+    }
+
+    #[test]
+    fn jpeg_oversized_exif_is_rejected_instead_of_truncated() {
+        let jpeg = vec![0xFF, 0xD8, 0xFF, 0xD9]; // minimal SOI/EOI
+        let metadata = ImageMetadata {
+            icc_profile: None,
+            exif: Some(vec![0u8; 70_000]),
+        };
+        assert!(insert_jpeg_metadata(&jpeg, &metadata).is_err());
+    }
+
+    #[test]
+    fn webp_lossy_alpha_metadata_does_not_nest_a_second_vp8x() {
+        // Lossy + alpha forces the `webp` crate's simple encoder to already
+        // emit its own extended VP8X/ALPH/VP8 container.
+        let image = solid_rgba(4, 4, Rgba([1, 2, 3, 128]));
+        let (width, height, has_alpha, encoded) = encode_webp(&image, &WebpQuality::Lossy(50.));
+        assert!(has_alpha);
+        assert_eq!(&encoded[12..16], b"VP8X");
+
+        let metadata = ImageMetadata {
+            icc_profile: Some(b"icc".to_vec()),
+            exif: Some(b"exif".to_vec()),
+        };
+        let with_metadata = insert_webp_metadata(&encoded, width, height, has_alpha, &metadata);
+
+        let vp8x_count = with_metadata
+            .windows(4)
+            .filter(|w| *w == b"VP8X")
+            .count();
+        assert_eq!(
+            vp8x_count, 1,
+            "metadata insertion must not nest a second VP8X container"
+        );
+    }
+
+    #[test]
+    fn animated_webp_lossy_alpha_frame_does_not_nest_a_second_vp8x() {
+        let frames = vec![
+            (solid_rgba(2, 2, Rgba([1, 2, 3, 64])), Duration::from_millis(100)),
+            (solid_rgba(2, 2, Rgba([4, 5, 6, 128])), Duration::from_millis(100)),
+        ];
+        let encoded = save_animated_webp(&frames, &WebpQuality::Lossy(50.), &LoopCount::Infinite);
+
+        let vp8x_count = encoded.windows(4).filter(|w| *w == b"VP8X").count();
+        assert_eq!(
+            vp8x_count, 1,
+            "only the outer container's VP8X should be present, not one per frame"
+        );
+    }
+
+    #[test]
+    fn save_animation_on_empty_frames_does_not_panic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.webp");
+        let encoder = FileEncoder::AnimatedWebP {
+            quality: WebpQuality::default(),
+            loop_count: LoopCount::Infinite,
+        };
+
+        assert!(encoder.save_animation(&[], &path).is_ok());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn matching_variant_disambiguates_animated_and_static_webp() {
+        let variants = vec![
+            FileEncoder::WebP {
+                quality: WebpQuality::default(),
+            },
+            FileEncoder::AnimatedWebP {
+                quality: WebpQuality::default(),
+                loop_count: LoopCount::Infinite,
+            },
+        ];
+        let path = Path::new("image.webp");
+
+        let single = FileEncoder::matching_variant(path, &variants, 1);
+        assert!(!single.is_animated());
+
+        let animated = FileEncoder::matching_variant(path, &variants, 5);
+        assert!(animated.is_animated());
+    }
 }